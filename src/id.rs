@@ -3,7 +3,35 @@ use serde::{
     de, de::MapAccess, de::Visitor, ser::SerializeMap, Deserialize, Deserializer, Serialize,
     Serializer,
 };
+use std::convert::TryFrom;
 use std::fmt;
+#[cfg(feature = "uuid")]
+use uuid::Uuid;
+
+/// Errors that can occur while converting to or from an [`ID`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum IdError {
+    /// The value could not be parsed as a `bson::oid::ObjectId`.
+    InvalidObjectId(String),
+    /// The `Bson` variant is not one that `ID` knows how to represent.
+    UnsupportedBson(Bson),
+    /// The `ID` was not an `ID::Uuid`.
+    #[cfg(feature = "uuid")]
+    NotAUuid(ID),
+}
+
+impl fmt::Display for IdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IdError::InvalidObjectId(s) => write!(f, "invalid ObjectId: {}", s),
+            IdError::UnsupportedBson(b) => write!(f, "unsupported Bson type used as ID: {:?}", b),
+            #[cfg(feature = "uuid")]
+            IdError::NotAUuid(id) => write!(f, "ID is not a Uuid: {:?}", id),
+        }
+    }
+}
+
+impl std::error::Error for IdError {}
 
 /// An ID as defined by the GraphQL specification
 ///
@@ -13,6 +41,8 @@ pub enum ID {
     ObjectId(ObjectId),
     String(String),
     I64(i64),
+    #[cfg(feature = "uuid")]
+    Uuid(Uuid),
 }
 
 impl Serialize for ID {
@@ -28,10 +58,95 @@ impl Serialize for ID {
             }
             ID::String(s) => serializer.serialize_str(s),
             ID::I64(i) => serializer.serialize_i64(i.clone()),
+            #[cfg(feature = "uuid")]
+            ID::Uuid(u) => serializer.serialize_str(&u.to_hyphenated().to_string()),
+        }
+    }
+}
+
+/// Which of MongoDB Extended JSON v2's two representations to emit.
+///
+/// `ID`'s own [`Serialize`] impl emits the relaxed form: `ObjectId`s are still
+/// `{"$oid": ...}` (Extended JSON doesn't relax that one), but `I64` is a bare
+/// JSON number. Canonical mode instead wraps `I64` as `{"$numberLong": "..."}`,
+/// matching what tools like `mongoexport --jsonFormat=canonical` produce.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ExtJsonMode {
+    Canonical,
+    Relaxed,
+}
+
+/// Wraps an `&ID` so it serializes under a chosen [`ExtJsonMode`] rather than
+/// `ID`'s default relaxed form.
+pub struct ExtJson<'a>(pub &'a ID, pub ExtJsonMode);
+
+impl<'a> Serialize for ExtJson<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match (self.0, self.1) {
+            (ID::I64(i), ExtJsonMode::Canonical) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry("$numberLong", &i.to_string())?;
+                map.end()
+            }
+            (id, _) => id.serialize(serializer),
         }
     }
 }
 
+#[cfg(test)]
+mod ext_json_tests {
+    use super::*;
+
+    #[test]
+    fn relaxed_mode_matches_default_serialize() {
+        let id = ID::I64(42);
+        let relaxed = serde_json::to_value(id.as_ext_json(ExtJsonMode::Relaxed)).unwrap();
+        let default = serde_json::to_value(&id).unwrap();
+        assert_eq!(relaxed, default);
+        assert_eq!(relaxed, serde_json::json!(42));
+    }
+
+    #[test]
+    fn canonical_mode_wraps_i64_as_number_long() {
+        let id = ID::I64(42);
+        let canonical = serde_json::to_value(id.as_ext_json(ExtJsonMode::Canonical)).unwrap();
+        assert_eq!(canonical, serde_json::json!({ "$numberLong": "42" }));
+    }
+
+    #[test]
+    fn canonical_mode_leaves_object_id_unchanged() {
+        let oid = ObjectId::new();
+        let id = ID::ObjectId(oid.clone());
+        let canonical = serde_json::to_value(id.as_ext_json(ExtJsonMode::Canonical)).unwrap();
+        assert_eq!(canonical, serde_json::json!({ "$oid": oid.to_string() }));
+    }
+}
+
+/// Parses a bare string scalar into an `ID`, recognizing the `$oid:` prefix
+/// (and, with the `uuid` feature, a 36-char UUID) before falling back to
+/// `ID::String`. Shared by every entry point — `serde`, the juniper scalar,
+/// and the async-graphql scalar — that accepts a string-typed ID.
+fn parse_id_str(s: String) -> ID {
+    if s.starts_with("$oid:") {
+        return match ObjectId::with_string(&s[5..]) {
+            Ok(oid) => ID::ObjectId(oid),
+            Err(_) => ID::String(s),
+        };
+    }
+    #[cfg(feature = "uuid")]
+    {
+        if s.len() == 36 {
+            if let Ok(u) = Uuid::parse_str(&s) {
+                return ID::Uuid(u);
+            }
+        }
+    }
+    ID::String(s)
+}
+
 struct IDVisitor;
 impl<'de> Visitor<'de> for IDVisitor {
     type Value = ID;
@@ -45,37 +160,46 @@ impl<'de> Visitor<'de> for IDVisitor {
         M: MapAccess<'de>,
     {
         // send this back into the Bson deserializer
-        Ok(ID::with_bson(&Bson::deserialize(
-            de::value::MapAccessDeserializer::new(access),
-        )?))
+        let value = Bson::deserialize(de::value::MapAccessDeserializer::new(access))?;
+
+        // `$oid` is already recognized by the Bson deserializer above. MongoDB
+        // Extended JSON v2's `$numberLong`/`$numberInt` aren't, so a map with
+        // just one of those keys comes back as a plain one-entry `Bson::Document`
+        // rather than a `Bson::I64` — unwrap it here.
+        if let Bson::Document(ref doc) = value {
+            if doc.len() == 1 {
+                if let Some(number) = doc.get("$numberLong").or_else(|| doc.get("$numberInt")) {
+                    return match number {
+                        Bson::String(s) => s
+                            .parse::<i64>()
+                            .map(ID::I64)
+                            .map_err(|_| de::Error::custom(format!("invalid number: {}", s))),
+                        Bson::I32(i) => Ok(ID::I64(*i as i64)),
+                        Bson::I64(i) => Ok(ID::I64(*i)),
+                        other => Err(de::Error::custom(format!(
+                            "invalid $numberLong/$numberInt value: {:?}",
+                            other
+                        ))),
+                    };
+                }
+            }
+        }
+
+        ID::try_from(&value).map_err(de::Error::custom)
     }
 
     fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
     where
         E: de::Error,
     {
-        if v.starts_with("$oid:") {
-            match ObjectId::with_string(&v[5..]) {
-                Ok(oid) => Ok(ID::ObjectId(oid)),
-                Err(_) => Ok(ID::String(v.into())),
-            }
-        } else {
-            Ok(ID::String(v.into()))
-        }
+        Ok(parse_id_str(v.to_owned()))
     }
 
     fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
     where
         E: de::Error,
     {
-        if v.starts_with("$oid:") {
-            match ObjectId::with_string(&v[5..]) {
-                Ok(oid) => Ok(ID::ObjectId(oid)),
-                Err(_) => Ok(ID::String(v)),
-            }
-        } else {
-            Ok(ID::String(v))
-        }
+        Ok(parse_id_str(v))
     }
 
     fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
@@ -120,6 +244,8 @@ impl From<ID> for String {
             ID::ObjectId(o) => o.to_hex(),
             ID::String(s) => s,
             ID::I64(i) => i.to_string(),
+            #[cfg(feature = "uuid")]
+            ID::Uuid(u) => u.to_hyphenated().to_string(),
         }
     }
 }
@@ -136,6 +262,25 @@ impl From<ObjectId> for ID {
     }
 }
 
+#[cfg(feature = "uuid")]
+impl From<Uuid> for ID {
+    fn from(u: Uuid) -> ID {
+        ID::Uuid(u)
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl std::convert::TryFrom<ID> for Uuid {
+    type Error = IdError;
+
+    fn try_from(id: ID) -> Result<Uuid, IdError> {
+        match id {
+            ID::Uuid(u) => Ok(u),
+            other => Err(IdError::NotAUuid(other)),
+        }
+    }
+}
+
 impl ID {
     pub fn from_string<S: Into<String>>(value: S) -> Self {
         ID::String(value.into())
@@ -154,17 +299,41 @@ impl ID {
         ID::ObjectId(value)
     }
 
+    #[deprecated(since = "0.2.0", note = "use `try_with_string_to_oid` instead")]
     pub fn with_string_to_oid<S: Into<String>>(value: S) -> Self {
         let id = ObjectId::with_string(&value.into()).unwrap();
         ID::ObjectId(id)
     }
 
+    /// Construct a new `ID::ObjectId` from anything implementing `Into<String>`,
+    /// returning an [`IdError`] instead of panicking on malformed input.
+    pub fn try_with_string_to_oid<S: Into<String>>(value: S) -> Result<Self, IdError> {
+        let value = value.into();
+        ObjectId::with_string(&value)
+            .map(ID::ObjectId)
+            .map_err(|_| IdError::InvalidObjectId(value))
+    }
+
+    #[deprecated(since = "0.2.0", note = "use `ID::try_from(bson)` instead")]
     pub fn with_bson(value: &Bson) -> Self {
-        match value.into() {
-            Bson::String(s) => ID::String(s),
-            Bson::ObjectId(o) => ID::ObjectId(o),
-            Bson::I64(i) => ID::I64(i),
-            _ => panic!("Invalid id type used {:?}", value),
+        ID::try_from(value).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Convert this `ID` into an `ObjectId`, returning an [`IdError`] if the
+    /// underlying value cannot be parsed as one instead of panicking.
+    pub fn try_to_oid(&self) -> Result<ObjectId, IdError> {
+        match self {
+            ID::ObjectId(o) => Ok(o.clone()),
+            ID::String(s) => {
+                ObjectId::with_string(s).map_err(|_| IdError::InvalidObjectId(s.clone()))
+            }
+            ID::I64(i) => ObjectId::with_string(&i.to_string())
+                .map_err(|_| IdError::InvalidObjectId(i.to_string())),
+            #[cfg(feature = "uuid")]
+            ID::Uuid(u) => {
+                let s = u.to_hyphenated().to_string();
+                ObjectId::with_string(&s).map_err(|_| IdError::InvalidObjectId(s))
+            }
         }
     }
 
@@ -173,14 +342,24 @@ impl ID {
             ID::ObjectId(o) => Bson::ObjectId(o.clone()),
             ID::String(s) => Bson::String(s.to_string()),
             ID::I64(i) => Bson::I64(i.clone()),
+            #[cfg(feature = "uuid")]
+            ID::Uuid(u) => Bson::Binary(bson::spec::BinarySubtype::Uuid, u.as_bytes().to_vec()),
         }
     }
 
+    /// Borrow this `ID` as a wrapper that serializes under `mode` instead of
+    /// the default relaxed Extended JSON form.
+    pub fn as_ext_json(&self, mode: ExtJsonMode) -> ExtJson<'_> {
+        ExtJson(self, mode)
+    }
+
     pub fn to_string(&self) -> String {
         match self {
             ID::ObjectId(o) => o.to_hex(),
             ID::String(s) => s.clone(),
             ID::I64(i) => i.to_string(),
+            #[cfg(feature = "uuid")]
+            ID::Uuid(u) => u.to_hyphenated().to_string(),
         }
     }
 }
@@ -199,20 +378,121 @@ impl From<ID> for juniper::ID {
             ID::ObjectId(o) => juniper::ID::new(o.to_hex()),
             ID::String(s) => juniper::ID::new(s),
             ID::I64(s) => juniper::ID::new(s.to_string()),
+            #[cfg(feature = "uuid")]
+            ID::Uuid(u) => juniper::ID::new(u.to_hyphenated().to_string()),
         }
     }
 }
 
-impl From<ID> for ObjectId {
-    fn from(id: ID) -> ObjectId {
-        match id {
-            ID::ObjectId(o) => o,
-            ID::String(s) => ObjectId::with_string(&s).unwrap(),
-            ID::I64(i) => ObjectId::with_string(&i.to_string()).unwrap(),
+// There's no infallible `From<ID> for ObjectId`: every non-`ObjectId` variant
+// can fail to parse as one, and the crate's core `TryFrom` blanket impl
+// (`impl<T, U: Into<T>> TryFrom<U> for T`) would conflict with a hand-written
+// `TryFrom<ID> for ObjectId` if an infallible `From` existed alongside it.
+// Use `ObjectId::try_from(id)` or `id.try_to_oid()` instead.
+impl TryFrom<ID> for ObjectId {
+    type Error = IdError;
+
+    fn try_from(id: ID) -> Result<ObjectId, IdError> {
+        id.try_to_oid()
+    }
+}
+
+impl TryFrom<&Bson> for ID {
+    type Error = IdError;
+
+    fn try_from(value: &Bson) -> Result<ID, IdError> {
+        match value.clone() {
+            Bson::String(s) => Ok(ID::String(s)),
+            Bson::ObjectId(o) => Ok(ID::ObjectId(o)),
+            Bson::I64(i) => Ok(ID::I64(i)),
+            #[cfg(feature = "uuid")]
+            Bson::Binary(bson::spec::BinarySubtype::Uuid, bytes) => Uuid::from_slice(&bytes)
+                .map(ID::Uuid)
+                .map_err(|_| IdError::UnsupportedBson(value.clone())),
+            other => Err(IdError::UnsupportedBson(other)),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "uuid"))]
+mod uuid_bson_tests {
+    use super::*;
+
+    #[test]
+    fn uuid_round_trips_through_bson_binary_subtype_uuid() {
+        let uuid = Uuid::parse_str("550e8400-e29b-41d4-a716-446655440000").unwrap();
+        let id = ID::Uuid(uuid);
+
+        let bson = id.to_bson();
+        match &bson {
+            Bson::Binary(bson::spec::BinarySubtype::Uuid, bytes) => {
+                assert_eq!(bytes.as_slice(), uuid.as_bytes());
+            }
+            other => panic!("expected Bson::Binary(Uuid, _), got {:?}", other),
+        }
+
+        assert_eq!(ID::try_from(&bson).unwrap(), id);
+    }
+}
+
+// `ID` already implements `Display` above, which async-graphql's own `ID` relies
+// on when formatting scalars. `Deref<Target = String>` isn't implemented: unlike
+// async-graphql's `ID`, ours isn't a newtype over a single `String` and has no
+// owned string to hand out a reference to for the `I64`/`ObjectId` variants.
+#[cfg(feature = "async-graphql")]
+#[async_graphql::Scalar]
+impl async_graphql::ScalarType for ID {
+    fn parse(value: async_graphql::Value) -> async_graphql::InputValueResult<Self> {
+        match value {
+            async_graphql::Value::String(s) => Ok(parse_id_str(s)),
+            async_graphql::Value::Number(n) => n
+                .as_i64()
+                .map(ID::I64)
+                .ok_or_else(|| "invalid numeric ID".into()),
+            value => Err(async_graphql::InputValueError::expected_type(value)),
+        }
+    }
+
+    fn to_value(&self) -> async_graphql::Value {
+        match self {
+            ID::ObjectId(o) => async_graphql::Value::String(format!("$oid:{}", o.to_hex())),
+            ID::String(s) => async_graphql::Value::String(s.clone()),
+            ID::I64(i) => async_graphql::Value::Number((*i).into()),
+            #[cfg(feature = "uuid")]
+            ID::Uuid(u) => async_graphql::Value::String(u.to_hyphenated().to_string()),
         }
     }
 }
 
+#[cfg(all(test, feature = "async-graphql"))]
+mod async_graphql_scalar_tests {
+    use super::*;
+    use async_graphql::ScalarType;
+
+    #[test]
+    fn object_id_round_trips_through_scalar_type() {
+        let oid = ObjectId::new();
+        let id = ID::ObjectId(oid.clone());
+        let value = id.to_value();
+        assert_eq!(value, async_graphql::Value::String(format!("$oid:{}", oid.to_hex())));
+        assert_eq!(ID::parse(value).unwrap(), id);
+    }
+
+    #[test]
+    fn string_round_trips_through_scalar_type() {
+        let id = ID::String("plain-id".to_string());
+        assert_eq!(ID::parse(id.to_value()).unwrap(), id);
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn uuid_round_trips_through_scalar_type() {
+        let uuid = Uuid::parse_str("550e8400-e29b-41d4-a716-446655440000").unwrap();
+        let id = ID::Uuid(uuid);
+        assert_eq!(ID::parse(id.to_value()).unwrap(), id);
+    }
+}
+
 #[cfg(feature = "graphql")]
 use juniper::{
     parser::{ParseError, ScalarToken, Token},
@@ -225,7 +505,21 @@ graphql_scalar!(ID as "ID" where Scalar = <S>{
         match self {
             ID::ObjectId(ref o) => Value::scalar(format!("$oid:{}", o.to_hex())),
             ID::String(ref s) =>  Value::scalar(s.clone()),
-            ID::I64(ref i) =>  Value::scalar(i.clone() as i32),
+            ID::I64(ref i) => {
+                // `DefaultScalarValue::Int` only holds an `i32`; anything outside
+                // that range is resolved as a `$i64:`-prefixed string (mirroring
+                // the `$oid:` convention above) so it round-trips losslessly
+                // instead of being silently truncated. The prefix is what lets
+                // `from_input_value` tell this apart from an ordinary string ID
+                // that merely looks numeric, e.g. `"007"`.
+                if *i >= i64::from(i32::MIN) && *i <= i64::from(i32::MAX) {
+                    Value::scalar(*i as i32)
+                } else {
+                    Value::scalar(format!("$i64:{}", i))
+                }
+            }
+            #[cfg(feature = "uuid")]
+            ID::Uuid(ref u) => Value::scalar(u.to_hyphenated().to_string()),
         }
     }
 
@@ -234,13 +528,17 @@ graphql_scalar!(ID as "ID" where Scalar = <S>{
             InputValue::Scalar(ref s) => {
                 match s.as_string() {
                     Some(s) => {
-                        if s.starts_with("$oid:") {
-                            match ObjectId::with_string(&s[5..]) {
-                                Ok(oid) => Some(ID::ObjectId(oid)),
+                        if s.starts_with("$i64:") {
+                            // Recover the full 64-bit value `resolve` encoded above;
+                            // only this explicit prefix is treated as a number, so an
+                            // ordinary numeric-looking string ID (e.g. `"007"`) is
+                            // never silently reinterpreted as an `I64`.
+                            match s[5..].parse::<i64>() {
+                                Ok(i) => Some(ID::I64(i)),
                                 Err(_) => Some(ID::String(s)),
                             }
                         } else {
-                            Some(ID::String(s))
+                            Some(parse_id_str(s))
                         }
                     },
                     None => s.as_int().map(|i| ID::I64(i as i64))
@@ -259,3 +557,33 @@ graphql_scalar!(ID as "ID" where Scalar = <S>{
         }
     }
 });
+
+#[cfg(all(test, feature = "graphql"))]
+mod graphql_scalar_tests {
+    use super::*;
+    use juniper::{FromInputValue, InputValue};
+
+    fn from_input_value(v: &InputValue<juniper::DefaultScalarValue>) -> Option<ID> {
+        <ID as FromInputValue<juniper::DefaultScalarValue>>::from_input_value(v)
+    }
+
+    #[test]
+    fn i64_above_i32_max_round_trips_through_from_input_value() {
+        let big = i64::from(i32::MAX) + 1;
+        let v = InputValue::scalar(format!("$i64:{}", big));
+        assert_eq!(from_input_value(&v), Some(ID::I64(big)));
+    }
+
+    #[test]
+    fn i64_below_i32_min_round_trips_through_from_input_value() {
+        let small = i64::from(i32::MIN) - 1;
+        let v = InputValue::scalar(format!("$i64:{}", small));
+        assert_eq!(from_input_value(&v), Some(ID::I64(small)));
+    }
+
+    #[test]
+    fn numeric_looking_string_id_is_not_reinterpreted_as_i64() {
+        let v = InputValue::scalar("007".to_string());
+        assert_eq!(from_input_value(&v), Some(ID::String("007".to_string())));
+    }
+}